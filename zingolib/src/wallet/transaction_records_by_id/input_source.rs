@@ -12,15 +12,318 @@ use zcash_primitives::{
 use zip32::AccountId;
 
 use crate::{
-    error::{ZingoLibError, ZingoLibResult},
+    error::ZingoLibError,
     wallet::{notes::ShNoteId, transaction_records_by_id::TransactionRecordsById},
 };
 
+/// The ZIP-317 marginal fee, in zatoshis, charged per logical action.
+const MARGINAL_FEE: u64 = 5000;
+/// The ZIP-317 grace number of logical actions covered by the base fee.
+const GRACE_ACTIONS: usize = 2;
+/// Change below this value (in zatoshis) is not worth creating; the privacy strategy pulls in
+/// an extra note rather than emit a dust change output.
+const DUST_THRESHOLD: u64 = MARGINAL_FEE;
+
+/// Selects how [`select_spendable_notes_with_strategy`] chooses which notes to spend.
+///
+/// [`select_spendable_notes_with_strategy`]:
+/// TransactionRecordsById::select_spendable_notes_with_strategy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    /// Purely value-greedy: spend Sapling before Orchard, biggest note first, stopping as soon
+    /// as the chosen inputs cover the target plus fee.
+    #[default]
+    Greedy,
+    /// Privacy-preserving: prefer keeping the spend within a single pool, prefer Orchard over
+    /// Sapling for its larger anonymity set, and avoid emitting dust change.
+    Privacy,
+}
+
+/// The shielded outputs a spend is assumed to produce when sizing the fee during selection:
+/// one for the recipient and one for change. They are budgeted against the Orchard pool,
+/// where the wallet directs change.
+const EXPECTED_SHIELDED_OUTPUTS: usize = 2;
+
+/// Computes the ZIP-317 conventional fee implied by selecting `n_sapling_in` Sapling and
+/// `n_orchard_in` Orchard inputs.
+///
+/// The logical-action count is `max(n_transparent_in, n_transparent_out) +
+/// max(n_sapling_in, n_sapling_out) + max(n_orchard_in, n_orchard_out)`. Note selection only
+/// contributes shielded inputs (so the transparent term is zero), but a spend still produces a
+/// recipient and a change output; those [`EXPECTED_SHIELDED_OUTPUTS`] actions are counted in the
+/// Orchard term so the fee is not under-estimated when few inputs are chosen.
+fn conventional_fee(
+    n_sapling_in: usize,
+    n_orchard_in: usize,
+) -> Result<NonNegativeAmount, ZingoLibError> {
+    let logical_actions = n_sapling_in + n_orchard_in.max(EXPECTED_SHIELDED_OUTPUTS);
+    NonNegativeAmount::from_u64(MARGINAL_FEE * GRACE_ACTIONS.max(logical_actions) as u64)
+        .map_err(|e| ZingoLibError::Error(e.to_string()))
+}
+
+impl TransactionRecordsById {
+    /// Like [`InputSource::select_spendable_notes`], but takes an explicit [`SelectionStrategy`]
+    /// so callers building autoshielding versus ordinary transfers can pick appropriate
+    /// behavior. The trait method delegates here with [`SelectionStrategy::Greedy`].
+    ///
+    /// Selection decisions are made over `(value, id)` pairs and the chosen notes are
+    /// materialized into `ReceivedNote`s only once the set is final.
+    ///
+    /// Only account zero is supported: the per-note `AccountId` field and the filter that would
+    /// scope selection to other accounts live in the note-record modules, which are not part of
+    /// this tree. Until that lands, a non-zero `account` hard-errors rather than silently
+    /// returning account zero's notes (a spend-authorization hazard).
+    pub fn select_spendable_notes_with_strategy(
+        &self,
+        account: AccountId,
+        target_value: NonNegativeAmount,
+        sources: &[ShieldedProtocol],
+        anchor_height: zcash_primitives::consensus::BlockHeight,
+        exclude: &[ShNoteId],
+        strategy: SelectionStrategy,
+    ) -> Result<SpendableNotes<ShNoteId>, ZingoLibError> {
+        if account != AccountId::ZERO {
+            return Err(ZingoLibError::Error(
+                "we don't use non-zero accounts (yet?)".to_string(),
+            ));
+        }
+        let mut sapling: Vec<(u64, ShNoteId)> = Vec::new();
+        let mut orchard: Vec<(u64, ShNoteId)> = Vec::new();
+        for transaction_record in self.values().filter(|transaction_record| {
+            transaction_record
+                .status
+                .is_confirmed_before_or_at(&anchor_height)
+        }) {
+            if sources.contains(&ShieldedProtocol::Sapling) {
+                sapling.extend(
+                    transaction_record
+                        .select_unspent_shnotes_and_ids::<SaplingDomain>()
+                        .into_iter()
+                        .filter(|(_, id)| !exclude.contains(id))
+                        .map(|(note, id)| (note.value().inner(), id)),
+                );
+            }
+            if sources.contains(&ShieldedProtocol::Orchard) {
+                orchard.extend(
+                    transaction_record
+                        .select_unspent_shnotes_and_ids::<OrchardDomain>()
+                        .into_iter()
+                        .filter(|(_, id)| !exclude.contains(id))
+                        .map(|(note, id)| (note.value().inner(), id)),
+                );
+            }
+        }
+        // Biggest note first, so we pull the fewest inputs and keep the logical-action count
+        // (and therefore the conventional fee) as small as possible.
+        sapling.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        orchard.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+
+        let total_available = sapling
+            .iter()
+            .chain(orchard.iter())
+            .fold(0u64, |acc, (value, _)| acc.saturating_add(*value));
+        if total_available < target_value.into_u64() {
+            return Err(ZingoLibError::Error(format!(
+                "insufficient funds even including dust, short {}",
+                target_value.into_u64().saturating_sub(total_available)
+            )));
+        }
+
+        // `required(sapling_in, orchard_in)` is the target plus the ZIP-317 conventional fee
+        // implied by the given input counts; it grows as more inputs are chosen.
+        let required = |sapling_in: usize, orchard_in: usize| -> Result<u64, ZingoLibError> {
+            Ok(target_value.into_u64() + conventional_fee(sapling_in, orchard_in)?.into_u64())
+        };
+
+        let (chosen_sapling, chosen_orchard) = match strategy {
+            SelectionStrategy::Greedy => {
+                // Sapling first, then Orchard — unconditional value-greedy selection.
+                let mut chosen_sapling = Vec::new();
+                let mut chosen_orchard = Vec::new();
+                let mut accumulated = 0u64;
+                for (value, id) in &sapling {
+                    if accumulated >= required(chosen_sapling.len(), chosen_orchard.len())? {
+                        break;
+                    }
+                    chosen_sapling.push(*id);
+                    accumulated = accumulated.saturating_add(*value);
+                }
+                for (value, id) in &orchard {
+                    if accumulated >= required(chosen_sapling.len(), chosen_orchard.len())? {
+                        break;
+                    }
+                    chosen_orchard.push(*id);
+                    accumulated = accumulated.saturating_add(*value);
+                }
+                if accumulated < required(chosen_sapling.len(), chosen_orchard.len())? {
+                    return Err(ZingoLibError::Error(format!(
+                        "insufficient funds at the required fee, short {}",
+                        required(chosen_sapling.len(), chosen_orchard.len())?
+                            .saturating_sub(accumulated)
+                    )));
+                }
+                (chosen_sapling, chosen_orchard)
+            }
+            SelectionStrategy::Privacy => {
+                // (a) Prefer staying within a single pool, (b) preferring Orchard over Sapling
+                // for its larger anonymity set, before combining pools as a last resort.
+                if let Some(chosen) = select_within_pool(&orchard, |n| required(0, n))? {
+                    (Vec::new(), chosen)
+                } else if let Some(chosen) = select_within_pool(&sapling, |n| required(n, 0))? {
+                    (chosen, Vec::new())
+                } else {
+                    // Neither pool can stand alone: combine, Orchard first.
+                    let mut chosen_orchard = Vec::new();
+                    let mut chosen_sapling = Vec::new();
+                    let mut accumulated = 0u64;
+                    for (value, id) in &orchard {
+                        if accumulated >= required(chosen_sapling.len(), chosen_orchard.len())? {
+                            break;
+                        }
+                        chosen_orchard.push(*id);
+                        accumulated = accumulated.saturating_add(*value);
+                    }
+                    for (value, id) in &sapling {
+                        if accumulated >= required(chosen_sapling.len(), chosen_orchard.len())? {
+                            break;
+                        }
+                        chosen_sapling.push(*id);
+                        accumulated = accumulated.saturating_add(*value);
+                    }
+                    if accumulated < required(chosen_sapling.len(), chosen_orchard.len())? {
+                        return Err(ZingoLibError::Error(format!(
+                            "insufficient funds at the required fee, short {}",
+                            required(chosen_sapling.len(), chosen_orchard.len())?
+                                .saturating_sub(accumulated)
+                        )));
+                    }
+                    // (c) Avoid emitting dust change in the combined case too: pull in an unused
+                    // note (Orchard first, then Sapling), recomputing `required` each time, until
+                    // the change clears the dust threshold, no unused note remains, or the note just
+                    // pulled in does not cover its added fee (it is then backed out).
+                    loop {
+                        let change =
+                            accumulated - required(chosen_sapling.len(), chosen_orchard.len())?;
+                        if change == 0 || change >= DUST_THRESHOLD {
+                            break;
+                        }
+                        // Remember which pool we pull from so we can back the note out if it does
+                        // not cover its own added marginal fee.
+                        let added_orchard = if let Some((value, id)) =
+                            orchard.get(chosen_orchard.len())
+                        {
+                            chosen_orchard.push(*id);
+                            accumulated = accumulated.saturating_add(*value);
+                            true
+                        } else if let Some((value, id)) = sapling.get(chosen_sapling.len()) {
+                            chosen_sapling.push(*id);
+                            accumulated = accumulated.saturating_add(*value);
+                            false
+                        } else {
+                            break;
+                        };
+                        if accumulated < required(chosen_sapling.len(), chosen_orchard.len())? {
+                            if added_orchard {
+                                if let Some(id) = chosen_orchard.pop() {
+                                    accumulated =
+                                        accumulated.saturating_sub(note_value(&orchard, id));
+                                }
+                            } else if let Some(id) = chosen_sapling.pop() {
+                                accumulated = accumulated.saturating_sub(note_value(&sapling, id));
+                            }
+                            break;
+                        }
+                    }
+                    (chosen_sapling, chosen_orchard)
+                }
+            }
+        };
+
+        let mut sapling_notes = Vec::<ReceivedNote<ShNoteId, sapling_crypto::Note>>::new();
+        for id in chosen_sapling {
+            sapling_notes.push(
+                self.get(&id.txid)
+                    .and_then(|tr| tr.get_received_note::<SaplingDomain>(id.index))
+                    .ok_or_else(|| ZingoLibError::Error("missing note".to_string()))?,
+            );
+        }
+        let mut orchard_notes = Vec::<ReceivedNote<ShNoteId, orchard::Note>>::new();
+        for id in chosen_orchard {
+            orchard_notes.push(
+                self.get(&id.txid)
+                    .and_then(|tr| tr.get_received_note::<OrchardDomain>(id.index))
+                    .ok_or_else(|| ZingoLibError::Error("missing note".to_string()))?,
+            );
+        }
+        Ok(SpendableNotes::new(sapling_notes, orchard_notes))
+    }
+}
+
+/// Attempts to cover a spend entirely from `pool` (biggest note first). `required` maps a
+/// candidate input count to the target plus conventional fee for that many single-pool inputs.
+///
+/// Returns `Ok(Some(ids))` when the pool alone suffices — pulling in an extra note if the
+/// change would otherwise be dust — and `Ok(None)` when it cannot stand alone.
+fn select_within_pool(
+    pool: &[(u64, ShNoteId)],
+    required: impl Fn(usize) -> Result<u64, ZingoLibError>,
+) -> Result<Option<Vec<ShNoteId>>, ZingoLibError> {
+    let mut chosen = Vec::new();
+    let mut accumulated = 0u64;
+    for (value, id) in pool {
+        if accumulated >= required(chosen.len())? {
+            break;
+        }
+        chosen.push(*id);
+        accumulated = accumulated.saturating_add(*value);
+    }
+    if accumulated < required(chosen.len())? {
+        return Ok(None);
+    }
+    // (c) Avoid emitting dust change. An extra note can raise the fee by one marginal action, so
+    // keep pulling notes in (recomputing `required` every time) until the change clears the dust
+    // threshold, there are no more notes to add, or the note just added does not cover its added
+    // fee (it is then backed out).
+    loop {
+        let required = required(chosen.len())?;
+        if accumulated < required {
+            // The extra note did not cover its own added fee; back it out and stop.
+            if let Some(id) = chosen.pop() {
+                accumulated = accumulated.saturating_sub(note_value(pool, id));
+            }
+            break;
+        }
+        let change = accumulated - required;
+        if change == 0 || change >= DUST_THRESHOLD {
+            break;
+        }
+        match pool.get(chosen.len()) {
+            Some((value, id)) => {
+                chosen.push(*id);
+                accumulated = accumulated.saturating_add(*value);
+            }
+            None => break,
+        }
+    }
+    Ok(Some(chosen))
+}
+
+/// Looks up the value of `id` within `pool`, or `0` if it is not present.
+fn note_value(pool: &[(u64, ShNoteId)], id: ShNoteId) -> u64 {
+    pool.iter()
+        .find_map(|(value, candidate)| (*candidate == id).then_some(*value))
+        .unwrap_or(0)
+}
+
 impl InputSource for TransactionRecordsById {
     type Error = ZingoLibError;
     type AccountId = zcash_primitives::zip32::AccountId;
     type NoteRef = ShNoteId;
 
+    // LIMITATION (chunk0-3): the returned `ReceivedNote` does not yet carry a
+    // `recipient_key_scope`. Persisting that scope on each note record at scan time and
+    // populating it here requires the note-record and scanning modules, which are not part of
+    // this source snapshot; it is therefore left unimplemented rather than faked.
     fn get_spendable_note(
         &self,
         txid: &zcash_primitives::transaction::TxId,
@@ -66,75 +369,14 @@ impl InputSource for TransactionRecordsById {
         anchor_height: zcash_primitives::consensus::BlockHeight,
         exclude: &[Self::NoteRef],
     ) -> Result<SpendableNotes<ShNoteId>, ZingoLibError> {
-        if account != AccountId::ZERO {
-            return Err(ZingoLibError::Error(
-                "we don't use non-zero accounts (yet?)".to_string(),
-            ));
-        }
-        let mut sapling_note_noteref_pairs: Vec<(sapling_crypto::Note, ShNoteId)> = Vec::new();
-        let mut orchard_note_noteref_pairs: Vec<(orchard::Note, ShNoteId)> = Vec::new();
-        for transaction_record in self.values().filter(|transaction_record| {
-            transaction_record
-                .status
-                .is_confirmed_before_or_at(&anchor_height)
-        }) {
-            if sources.contains(&ShieldedProtocol::Sapling) {
-                sapling_note_noteref_pairs.extend(
-                    transaction_record
-                        .select_unspent_shnotes_and_ids::<SaplingDomain>()
-                        .into_iter()
-                        .filter(|note_ref_pair| !exclude.contains(&note_ref_pair.1)),
-                );
-            }
-            if sources.contains(&ShieldedProtocol::Orchard) {
-                orchard_note_noteref_pairs.extend(
-                    transaction_record
-                        .select_unspent_shnotes_and_ids::<OrchardDomain>()
-                        .into_iter()
-                        .filter(|note_ref_pair| !exclude.contains(&note_ref_pair.1)),
-                );
-            }
-        }
-        let mut sapling_notes = Vec::<ReceivedNote<ShNoteId, sapling_crypto::Note>>::new();
-        let mut orchard_notes = Vec::<ReceivedNote<ShNoteId, orchard::Note>>::new();
-        if let Some(missing_value_after_sapling) = sapling_note_noteref_pairs.into_iter().rev(/*biggest first*/).try_fold(
-            Some(target_value),
-            |rolling_target, (note, noteref)| match rolling_target {
-                Some(targ) => {
-                    sapling_notes.push(
-                        self.get(&noteref.txid).and_then(|tr| tr.get_received_note::<SaplingDomain>(noteref.index))
-                            .ok_or_else(|| ZingoLibError::Error("missing note".to_string()))?
-                    );
-                    Ok(targ
-                        - NonNegativeAmount::from_u64(note.value().inner())
-                            .map_err(|e| ZingoLibError::Error(e.to_string()))?)
-                }
-                None => Ok(None),
-            },
-        )? {
-            if let Some(missing_value_after_orchard) = orchard_note_noteref_pairs.into_iter().rev(/*biggest first*/).try_fold(
-            Some(missing_value_after_sapling),
-            |rolling_target, (note, noteref)| match rolling_target {
-                Some(targ) => {
-                    orchard_notes.push(
-                        self.get(&noteref.txid).and_then(|tr| tr.get_received_note::<OrchardDomain>(noteref.index))
-                            .ok_or_else(|| ZingoLibError::Error("missing note".to_string()))?
-                    );
-                    Ok(targ
-                        - NonNegativeAmount::from_u64(note.value().inner())
-                            .map_err(|e| ZingoLibError::Error(e.to_string()))?)
-                }
-                None => Ok(None),
-            },
-        )? {
-                return ZingoLibResult::Err(ZingoLibError::Error(format!(
-                    "insufficient funds, short {}",
-                    missing_value_after_orchard.into_u64()
-                )));
-            };
-        };
-
-        Ok(SpendableNotes::new(sapling_notes, orchard_notes))
+        self.select_spendable_notes_with_strategy(
+            account,
+            target_value,
+            sources,
+            anchor_height,
+            exclude,
+            SelectionStrategy::default(),
+        )
     }
 
     fn get_unspent_transparent_output(
@@ -174,12 +416,13 @@ impl InputSource for TransactionRecordsById {
     }
     fn get_unspent_transparent_outputs(
         &self,
-        // I don't understand what this argument is for. Is the Trait's intent to only shield
-        // utxos from one address at a time? Is this needed?
-        _address: &zcash_primitives::legacy::TransparentAddress,
+        // Only sweep UTXOs received by this transparent receiver, so callers can build a
+        // per-address shielding proposal one receiver at a time.
+        address: &zcash_primitives::legacy::TransparentAddress,
         max_height: zcash_primitives::consensus::BlockHeight,
         exclude: &[zcash_primitives::transaction::components::OutPoint],
     ) -> Result<Vec<zcash_client_backend::wallet::WalletTransparentOutput>, Self::Error> {
+        let address_script = address.script();
         self.values()
             .filter_map(|transaction_record| {
                 transaction_record
@@ -192,6 +435,7 @@ impl InputSource for TransactionRecordsById {
                 transaction_record
                     .transparent_outputs
                     .iter()
+                    .filter(|output| output.script == address_script.0)
                     .filter(|output| {
                         exclude
                             .iter()
@@ -233,33 +477,12 @@ mod tests {
     };
     use zip32::AccountId;
 
+    use super::super::test_helpers::setup_mock_trbid;
     use crate::{
         test_framework::mocks::{default_txid, SaplingCryptoNoteBuilder},
-        wallet::{
-            notes::{
-                sapling::mocks::SaplingNoteBuilder, transparent::mocks::TransparentOutputBuilder,
-                ShNoteId,
-            },
-            transaction_record::mocks::TransactionRecordBuilder,
-            transaction_records_by_id::TransactionRecordsById,
-        },
+        wallet::notes::{transparent::mocks::TransparentOutputBuilder, ShNoteId},
     };
 
-    fn setup_mock_trbid() -> TransactionRecordsById {
-        let mut transaction_record = TransactionRecordBuilder::default().build();
-        transaction_record
-            .sapling_notes
-            .push(SaplingNoteBuilder::default().build());
-        let transparent_output = TransparentOutputBuilder::default().build();
-        transaction_record
-            .transparent_outputs
-            .push(transparent_output.clone());
-
-        let mut transaction_records_by_id = TransactionRecordsById::new();
-        transaction_records_by_id.insert_transaction_record(transaction_record);
-        transaction_records_by_id
-    }
-
     #[test]
     fn get_individual_sapling_note() {
         let transaction_records_by_id = setup_mock_trbid();
@@ -302,6 +525,31 @@ mod tests {
         )
     }
 
+    #[test]
+    fn privacy_strategy_selects_within_a_single_pool() {
+        use super::SelectionStrategy;
+
+        let transaction_records_by_id = setup_mock_trbid();
+
+        let target_value = NonNegativeAmount::const_from_u64(20000);
+        let anchor_height: BlockHeight = 10.into();
+        let spendable_notes = transaction_records_by_id
+            .select_spendable_notes_with_strategy(
+                AccountId::ZERO,
+                target_value,
+                &[ShieldedProtocol::Sapling, ShieldedProtocol::Orchard],
+                anchor_height,
+                &[],
+                SelectionStrategy::Privacy,
+            )
+            .unwrap();
+        assert!(spendable_notes.orchard().is_empty());
+        assert_eq!(
+            spendable_notes.sapling().first().unwrap().note().value(),
+            SaplingCryptoNoteBuilder::default().build().value()
+        )
+    }
+
     #[test]
     fn get_transparent_output() {
         let transaction_records_by_id = setup_mock_trbid();
@@ -0,0 +1,36 @@
+//! The [`TransactionRecordsById`] map and the selection subsystems built on top of it.
+//!
+//! NOTE: the `TransactionRecordsById` type itself and its inherent impls are defined in this
+//! module in the full crate; that definition is outside this source snapshot, which carries only
+//! the `input_source` and `proposal` submodules.
+
+mod input_source;
+pub mod proposal;
+
+/// Shared mock-wallet fixtures for this module's submodules, so the mock-wallet setup is defined
+/// once rather than copied per test module.
+#[cfg(test)]
+pub(crate) mod test_helpers {
+    use crate::wallet::{
+        notes::{sapling::mocks::SaplingNoteBuilder, transparent::mocks::TransparentOutputBuilder},
+        transaction_record::mocks::TransactionRecordBuilder,
+        transaction_records_by_id::TransactionRecordsById,
+    };
+
+    /// A [`TransactionRecordsById`] holding one confirmed record with a single Sapling note and a
+    /// single transparent output, built from the default mock builders.
+    pub(crate) fn setup_mock_trbid() -> TransactionRecordsById {
+        let mut transaction_record = TransactionRecordBuilder::default().build();
+        transaction_record
+            .sapling_notes
+            .push(SaplingNoteBuilder::default().build());
+        let transparent_output = TransparentOutputBuilder::default().build();
+        transaction_record
+            .transparent_outputs
+            .push(transparent_output.clone());
+
+        let mut transaction_records_by_id = TransactionRecordsById::new();
+        transaction_records_by_id.insert_transaction_record(transaction_record);
+        transaction_records_by_id
+    }
+}
@@ -0,0 +1,297 @@
+//! Serialization of the result of note/UTXO selection into a stable, versioned protobuf
+//! `Proposal` message and back.
+//!
+//! This lets one process perform selection against [`TransactionRecordsById`] and emit a
+//! self-contained blob — carrying the txid, pool, and output index of every selected
+//! [`ShNoteId`] plus the chosen transparent outputs — that a separate signing process
+//! (potentially on an air-gapped device) can reconstruct without access to the live wallet
+//! state.
+//!
+//! [`TransactionRecordsById`]: crate::wallet::transaction_records_by_id::TransactionRecordsById
+
+use zcash_client_backend::{data_api::SpendableNotes, wallet::WalletTransparentOutput, ShieldedProtocol};
+use zcash_primitives::{
+    consensus::BlockHeight,
+    legacy::Script,
+    transaction::{
+        components::{amount::NonNegativeAmount, OutPoint, TxOut},
+        TxId,
+    },
+};
+
+use crate::{
+    error::{ZingoLibError, ZingoLibResult},
+    wallet::notes::ShNoteId,
+};
+
+/// Bumped whenever the wire layout of [`Proposal`] changes incompatibly.
+pub const PROPOSAL_SERIALIZATION_VERSION: u32 = 1;
+
+/// The shielded pool a selected note lives in, mirrored on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, prost::Enumeration)]
+#[repr(i32)]
+pub enum Pool {
+    Sapling = 0,
+    Orchard = 1,
+}
+
+impl From<ShieldedProtocol> for Pool {
+    fn from(protocol: ShieldedProtocol) -> Self {
+        match protocol {
+            ShieldedProtocol::Sapling => Pool::Sapling,
+            ShieldedProtocol::Orchard => Pool::Orchard,
+        }
+    }
+}
+
+impl From<Pool> for ShieldedProtocol {
+    fn from(pool: Pool) -> Self {
+        match pool {
+            Pool::Sapling => ShieldedProtocol::Sapling,
+            Pool::Orchard => ShieldedProtocol::Orchard,
+        }
+    }
+}
+
+/// A single selected shielded note, addressed by the fields of its [`ShNoteId`].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProposedNote {
+    #[prost(bytes = "vec", tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(enumeration = "Pool", tag = "2")]
+    pub pool: i32,
+    #[prost(uint32, tag = "3")]
+    pub output_index: u32,
+}
+
+/// A single selected transparent output, self-contained enough to re-derive its
+/// [`WalletTransparentOutput`] offline.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProposedTransparentOutput {
+    #[prost(bytes = "vec", tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub output_index: u32,
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+    #[prost(bytes = "vec", tag = "4")]
+    pub script: Vec<u8>,
+    #[prost(uint32, tag = "5")]
+    pub height: u32,
+}
+
+/// A versioned, self-contained description of a selection result, ready to be encoded with
+/// [`prost::Message::encode_to_vec`] and shipped to an offline signer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Proposal {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint64, tag = "2")]
+    pub target_value: u64,
+    #[prost(uint32, tag = "3")]
+    pub anchor_height: u32,
+    #[prost(uint64, tag = "4")]
+    pub fee: u64,
+    #[prost(message, repeated, tag = "5")]
+    pub notes: Vec<ProposedNote>,
+    #[prost(message, repeated, tag = "6")]
+    pub transparent_outputs: Vec<ProposedTransparentOutput>,
+}
+
+/// The selection result in wallet-native types, recovered from a [`Proposal`] by the signer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposedInputs {
+    pub shielded_notes: Vec<ShNoteId>,
+    pub transparent_outputs: Vec<WalletTransparentOutput>,
+    pub target_value: NonNegativeAmount,
+    pub anchor_height: BlockHeight,
+    pub fee: NonNegativeAmount,
+}
+
+impl Proposal {
+    /// Builds a [`Proposal`] from the output of note/UTXO selection.
+    pub fn from_parts(
+        spendable_notes: &SpendableNotes<ShNoteId>,
+        transparent_outputs: &[WalletTransparentOutput],
+        target_value: NonNegativeAmount,
+        anchor_height: BlockHeight,
+        fee: NonNegativeAmount,
+    ) -> Self {
+        let mut notes = Vec::new();
+        for received in spendable_notes.sapling() {
+            notes.push(proposed_note(received.internal_note_id()));
+        }
+        for received in spendable_notes.orchard() {
+            notes.push(proposed_note(received.internal_note_id()));
+        }
+        let transparent_outputs = transparent_outputs
+            .iter()
+            .map(|output| ProposedTransparentOutput {
+                txid: output.outpoint().hash().to_vec(),
+                output_index: output.outpoint().n(),
+                value: output.txout().value.into_u64(),
+                script: output.txout().script_pubkey.0.clone(),
+                height: u32::from(output.height()),
+            })
+            .collect();
+        Proposal {
+            version: PROPOSAL_SERIALIZATION_VERSION,
+            target_value: target_value.into_u64(),
+            anchor_height: u32::from(anchor_height),
+            fee: fee.into_u64(),
+            notes,
+            transparent_outputs,
+        }
+    }
+}
+
+fn proposed_note(note_id: &ShNoteId) -> ProposedNote {
+    ProposedNote {
+        txid: note_id.txid.as_ref().to_vec(),
+        pool: Pool::from(note_id.shpool) as i32,
+        output_index: note_id.index,
+    }
+}
+
+impl TryFrom<Proposal> for ProposedInputs {
+    type Error = ZingoLibError;
+
+    fn try_from(proposal: Proposal) -> ZingoLibResult<Self> {
+        if proposal.version != PROPOSAL_SERIALIZATION_VERSION {
+            return Err(ZingoLibError::Error(format!(
+                "unsupported proposal version {}, expected {}",
+                proposal.version, PROPOSAL_SERIALIZATION_VERSION
+            )));
+        }
+        let shielded_notes = proposal
+            .notes
+            .into_iter()
+            .map(|note| {
+                Ok(ShNoteId {
+                    txid: txid_from_bytes(&note.txid)?,
+                    shpool: Pool::try_from(note.pool)
+                        .map_err(|e| ZingoLibError::Error(e.to_string()))?
+                        .into(),
+                    index: note.output_index,
+                })
+            })
+            .collect::<ZingoLibResult<Vec<_>>>()?;
+        let transparent_outputs = proposal
+            .transparent_outputs
+            .into_iter()
+            .map(|output| {
+                let value = NonNegativeAmount::from_u64(output.value)
+                    .map_err(|e| ZingoLibError::Error(e.to_string()))?;
+                let mut hash = [0u8; 32];
+                if output.txid.len() != hash.len() {
+                    return Err(ZingoLibError::Error("malformed outpoint txid".to_string()));
+                }
+                hash.copy_from_slice(&output.txid);
+                WalletTransparentOutput::from_parts(
+                    OutPoint::new(hash, output.output_index),
+                    TxOut {
+                        value,
+                        script_pubkey: Script(output.script),
+                    },
+                    BlockHeight::from_u32(output.height),
+                )
+                .ok_or_else(|| ZingoLibError::Error("malformed transparent output".to_string()))
+            })
+            .collect::<ZingoLibResult<Vec<_>>>()?;
+        Ok(ProposedInputs {
+            shielded_notes,
+            transparent_outputs,
+            target_value: NonNegativeAmount::from_u64(proposal.target_value)
+                .map_err(|e| ZingoLibError::Error(e.to_string()))?,
+            anchor_height: BlockHeight::from_u32(proposal.anchor_height),
+            fee: NonNegativeAmount::from_u64(proposal.fee)
+                .map_err(|e| ZingoLibError::Error(e.to_string()))?,
+        })
+    }
+}
+
+fn txid_from_bytes(bytes: &[u8]) -> ZingoLibResult<TxId> {
+    let mut hash = [0u8; 32];
+    if bytes.len() != hash.len() {
+        return Err(ZingoLibError::Error("malformed note txid".to_string()));
+    }
+    hash.copy_from_slice(bytes);
+    Ok(TxId::from_bytes(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message as _;
+    use zcash_client_backend::{data_api::SpendableNotes, ShieldedProtocol};
+    use zcash_primitives::{
+        consensus::BlockHeight, legacy::TransparentAddress,
+        transaction::components::amount::NonNegativeAmount,
+    };
+    use zip32::AccountId;
+
+    use super::super::test_helpers::setup_mock_trbid;
+    use super::{Proposal, ProposedInputs, PROPOSAL_SERIALIZATION_VERSION};
+    use crate::{test_framework::mocks::default_txid, wallet::notes::ShNoteId};
+
+    #[test]
+    fn proposal_round_trips() {
+        use zcash_client_backend::data_api::InputSource as _;
+
+        let transaction_records_by_id = setup_mock_trbid();
+        let target_value = NonNegativeAmount::const_from_u64(20000);
+        let anchor_height: BlockHeight = 10.into();
+        let spendable_notes: SpendableNotes<ShNoteId> = transaction_records_by_id
+            .select_spendable_notes(
+                AccountId::ZERO,
+                target_value,
+                &[ShieldedProtocol::Sapling, ShieldedProtocol::Orchard],
+                anchor_height,
+                &[],
+            )
+            .unwrap();
+        let transparent_outputs = transaction_records_by_id
+            .get_unspent_transparent_outputs(
+                &TransparentAddress::ScriptHash([0; 20]),
+                BlockHeight::from_u32(10),
+                &[],
+            )
+            .unwrap();
+        let fee = NonNegativeAmount::const_from_u64(10000);
+
+        let proposal = Proposal::from_parts(
+            &spendable_notes,
+            &transparent_outputs,
+            target_value,
+            anchor_height,
+            fee,
+        );
+        assert_eq!(proposal.version, PROPOSAL_SERIALIZATION_VERSION);
+
+        let bytes = proposal.encode_to_vec();
+        let decoded = Proposal::decode(bytes.as_slice()).unwrap();
+        assert_eq!(proposal, decoded);
+
+        let recovered = ProposedInputs::try_from(decoded).unwrap();
+        assert_eq!(recovered.target_value, target_value);
+        assert_eq!(recovered.anchor_height, anchor_height);
+        assert_eq!(recovered.fee, fee);
+        assert_eq!(
+            recovered.shielded_notes.first().unwrap().txid,
+            default_txid()
+        );
+        assert_eq!(
+            recovered.transparent_outputs.first().unwrap().outpoint(),
+            transparent_outputs.first().unwrap().outpoint()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut proposal = Proposal {
+            version: PROPOSAL_SERIALIZATION_VERSION,
+            ..Default::default()
+        };
+        proposal.version = PROPOSAL_SERIALIZATION_VERSION + 1;
+        assert!(ProposedInputs::try_from(proposal).is_err());
+    }
+}